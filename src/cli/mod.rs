@@ -0,0 +1,748 @@
+use std::io::{self, IsTerminal, Read, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::runtime::Runtime;
+use crate::credentials::{AppConfig, PasswordSource};
+use crate::docker::{is_missing, DockerManager, LogChunk};
+use crate::metrics;
+use crate::tunnel::SshTunnel;
+
+/// Passphrase used by the headless subcommands
+pub const PASSPHRASE_ENV_VAR: &str = "DB_MANAGER_PASSPHRASE";
+
+// Set by --quiet: only the requested output is printed
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// Print a progress or confirmation line to stdout, unless `--quiet` was given.
+/// Requested output (tables, JSON) and errors are printed directly.
+macro_rules! status {
+    ($($arg:tt)*) => {
+        if !$crate::cli::is_quiet() {
+            println!($($arg)*);
+        }
+    };
+}
+pub(crate) use status;
+
+/// Read the passphrase from a file, or stdin for "-". Only the trailing
+/// newline is stripped. `/dev/fd/N` works for passing a file descriptor.
+fn read_passphrase_file(path: &str) -> Result<String, anyhow::Error> {
+    let mut passphrase = String::new();
+    if path == "-" {
+        io::stdin().read_to_string(&mut passphrase)?;
+    } else {
+        passphrase = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Cannot read passphrase file '{}': {}", path, e))?;
+    }
+    if passphrase.ends_with('\n') {
+        passphrase.pop();
+        if passphrase.ends_with('\r') {
+            passphrase.pop();
+        }
+    }
+    Ok(passphrase)
+}
+
+/// The passphrase from `--passphrase-file` or the environment
+fn read_passphrase(passphrase_file: Option<&str>) -> Result<String, anyhow::Error> {
+    match passphrase_file {
+        Some(path) => read_passphrase_file(path),
+        None => std::env::var(PASSPHRASE_ENV_VAR).map_err(|_| {
+            anyhow::anyhow!("Set {} or pass --passphrase-file <path>", PASSPHRASE_ENV_VAR)
+        }),
+    }
+}
+
+/// Load the config with the passphrase from `--passphrase-file` or the environment
+fn authenticate(passphrase_file: Option<&str>) -> Result<(AppConfig, String), anyhow::Error> {
+    let passphrase = read_passphrase(passphrase_file)?;
+    let config = AppConfig::load(&passphrase)?;
+    Ok((config, passphrase))
+}
+
+/// Ask a y/N question on the terminal
+pub fn confirm(prompt: &str) -> Result<bool, anyhow::Error> {
+    print!("{} (y/N): ", prompt);
+    io::stdout().flush()?;
+
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+    let answer = input.trim().to_lowercase();
+    Ok(answer == "y" || answer == "yes")
+}
+
+/// Read a line from the terminal without echoing it
+fn prompt_hidden(prompt: &str) -> Result<String, anyhow::Error> {
+    use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+
+    print!("{}", prompt);
+    io::stdout().flush()?;
+    crossterm::terminal::enable_raw_mode()?;
+    let mut input = String::new();
+    let result = loop {
+        match event::read() {
+            Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => match key.code {
+                KeyCode::Enter => break Ok(input),
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    break Err(anyhow::anyhow!("Cancelled"));
+                }
+                KeyCode::Char(c) => input.push(c),
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                _ => {}
+            },
+            Ok(_) => {}
+            Err(e) => break Err(e.into()),
+        }
+    };
+    crossterm::terminal::disable_raw_mode()?;
+    println!();
+    result
+}
+
+/// The share password from `--share-password-file`, or asked for on the
+/// terminal (twice when `confirm_it` is set)
+fn read_share_password(path: Option<&str>, confirm_it: bool) -> Result<String, anyhow::Error> {
+    if let Some(path) = path {
+        return read_passphrase_file(path);
+    }
+    if !io::stdin().is_terminal() {
+        return Err(anyhow::anyhow!("Pass --share-password-file <path> in non-interactive mode"));
+    }
+    let password = prompt_hidden("Share password: ")?;
+    if confirm_it && prompt_hidden("Repeat share password: ")? != password {
+        return Err(anyhow::anyhow!("The share passwords don't match"));
+    }
+    Ok(password)
+}
+
+/// `db-tool delete <name> [--keep-volume] [--yes] [--passphrase-file <path>]`
+pub fn delete(args: &[String]) -> Result<(), anyhow::Error> {
+    let mut name = None;
+    let mut keep_volume = false;
+    let mut yes = false;
+    let mut passphrase_file = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--keep-volume" => keep_volume = true,
+            "--yes" | "-y" => yes = true,
+            "--passphrase-file" => {
+                let path = args.next().ok_or_else(|| anyhow::anyhow!("Missing value for --passphrase-file"))?;
+                passphrase_file = Some(path.as_str());
+            }
+            flag if flag.starts_with('-') => return Err(anyhow::anyhow!("Unknown option for delete: {}", flag)),
+            value if name.is_none() => name = Some(value.to_string()),
+            value => return Err(anyhow::anyhow!("Unexpected argument: {}", value)),
+        }
+    }
+    let name = name.ok_or_else(|| anyhow::anyhow!("Usage: db-tool delete <name> [--keep-volume] [--yes]"))?;
+
+    let (mut config, passphrase) = authenticate(passphrase_file)?;
+    let info = config.get_database(&name, &passphrase)?;
+    // A volume attached by name or adopted with its container isn't ours to delete
+    let owns_volume = config.owns_volume(&name);
+    // Only the entry is ours; the database itself runs elsewhere
+    if info.external {
+        if !yes {
+            if !io::stdin().is_terminal() {
+                return Err(anyhow::anyhow!("Refusing to delete '{}' without --yes in non-interactive mode", name));
+            }
+            if !confirm(&format!("⚠️  Remove the external database '{}' from the config?", name))? {
+                status!("Delete cancelled.");
+                return Ok(());
+            }
+        }
+        return config.remove_database(&name);
+    }
+
+    if !yes {
+        if !io::stdin().is_terminal() {
+            return Err(anyhow::anyhow!("Refusing to delete '{}' without --yes in non-interactive mode", name));
+        }
+        let prompt = if keep_volume {
+            format!("⚠️  Delete '{}' and its container? The volume '{}' is kept.", name, info.volume)
+        } else if !owns_volume {
+            format!("⚠️  Delete '{}' and its container? The volume '{}' wasn't created by db-tool and is kept.", name, info.volume)
+        } else {
+            format!("⚠️  Delete '{}', its container and ALL DATA in volume '{}'?", name, info.volume)
+        };
+        if !confirm(&prompt)? {
+            status!("Delete cancelled.");
+            return Ok(());
+        }
+    }
+
+    let rt = Runtime::new()?;
+    let docker_manager = DockerManager::new()?;
+    rt.block_on(async {
+        // Deleting something that is already gone shouldn't fail a teardown
+        match docker_manager.remove_container(&info.container_id, false).await {
+            Ok(()) => status!("✓ Container removed"),
+            Err(e) if is_missing(&e) => status!("Container already removed"),
+            Err(e) => return Err(e),
+        }
+        if !keep_volume && !owns_volume {
+            status!("Volume '{}' kept: it wasn't created by db-tool", info.volume);
+        } else if !keep_volume {
+            match docker_manager.remove_volume(&info.volume).await {
+                Ok(()) => status!("✓ Volume '{}' removed", info.volume),
+                Err(e) if is_missing(&e) => status!("Volume '{}' already removed", info.volume),
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    })?;
+
+    config.remove_database(&name)
+}
+
+/// `db-tool list [--no-auth] [--passphrase-file <path>]`: one line per
+/// database. With `--no-auth` no passphrase is needed and only the plaintext
+/// metadata is shown (name, type, container, creation date), never credentials.
+pub fn list(args: &[String]) -> Result<(), anyhow::Error> {
+    let mut no_auth = false;
+    let mut passphrase_file = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--no-auth" => no_auth = true,
+            "--passphrase-file" => {
+                let path = args.next().ok_or_else(|| anyhow::anyhow!("Missing value for --passphrase-file"))?;
+                passphrase_file = Some(path.as_str());
+            }
+            other => return Err(anyhow::anyhow!("Unknown option for list: {}", other)),
+        }
+    }
+
+    let mut rows = vec![["NAME", "TYPE", "PORT", "CONTAINER", "CREATED"].map(String::from)];
+    if no_auth {
+        let config = AppConfig::load_metadata()?;
+        for name in config.list_databases() {
+            let (db_type, container_id, created_at) = config.get_database_info(&name)?;
+            rows.push([
+                name.clone(),
+                db_type.to_string(),
+                "-".to_string(), // stored encrypted with the credentials
+                container_id.chars().take(12).collect(),
+                created_at.format("%Y-%m-%d %H:%M").to_string(),
+            ]);
+        }
+    } else {
+        let (config, passphrase) = authenticate(passphrase_file)?;
+        let mut databases = config.get_all_databases(&passphrase)?;
+        databases.sort_by(|a, b| a.name.cmp(&b.name));
+        for db in databases {
+            rows.push([
+                db.name.clone(),
+                db.db_type.template_name().to_string(),
+                db.credentials.port.to_string(),
+                if db.external { "external".to_string() } else { db.container_id.chars().take(12).collect() },
+                db.created_at.format("%Y-%m-%d %H:%M").to_string(),
+            ]);
+        }
+    }
+
+    let mut widths = [0; 5];
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    for row in &rows {
+        let cells: Vec<String> = row.iter().zip(widths).map(|(cell, width)| format!("{:<width$}", cell)).collect();
+        println!("{}", cells.join("  ").trim_end());
+    }
+    Ok(())
+}
+
+/// `db-tool export-db <name> [--json] [--show-secrets] [--passphrase-file <path>]`:
+/// print one database's record as JSON. Passwords are null and the connection
+/// string is masked unless `--show-secrets` is given.
+pub fn export_db(args: &[String]) -> Result<(), anyhow::Error> {
+    let mut name = None;
+    let mut show_secrets = false;
+    let mut passphrase_file = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--json" => {} // the only format, accepted for clarity in scripts
+            "--show-secrets" => show_secrets = true,
+            "--passphrase-file" => {
+                let path = args.next().ok_or_else(|| anyhow::anyhow!("Missing value for --passphrase-file"))?;
+                passphrase_file = Some(path.as_str());
+            }
+            flag if flag.starts_with('-') => return Err(anyhow::anyhow!("Unknown option for export-db: {}", flag)),
+            value if name.is_none() => name = Some(value.to_string()),
+            value => return Err(anyhow::anyhow!("Unexpected argument: {}", value)),
+        }
+    }
+    let name = name.ok_or_else(|| anyhow::anyhow!("Usage: db-tool export-db <name> [--json] [--show-secrets]"))?;
+
+    let (config, passphrase) = authenticate(passphrase_file)?;
+    let info = config.get_database(&name, &passphrase)?;
+    let settings = config.container_settings(&name)?;
+    let credentials = &info.credentials;
+
+    // A struct rather than json! so the fields keep this order
+    #[derive(serde::Serialize)]
+    struct Record<'a> {
+        name: &'a str,
+        #[serde(rename = "type")]
+        db_type: &'a str,
+        username: &'a str,
+        password: Option<&'a str>,
+        database: &'a str,
+        port: u16,
+        root_password: Option<&'a str>,
+        connection_string: String,
+        created_at: chrono::DateTime<chrono::Utc>,
+        container_id: &'a str,
+        image: &'a str,
+        volume: &'a str,
+        connect_database: Option<&'a str>,
+        connection_template: Option<&'a str>,
+        password_source: Option<String>, // as the wizard takes it, e.g. "env:DB_PASSWORD"
+        secret_files: bool,
+        shm_size: Option<u64>,
+        tls: bool,
+        replica_set: bool,
+        timezone: Option<&'a str>,
+        memory: Option<u64>,
+        memory_swap: Option<i64>,
+        oom_kill_disable: bool,
+        network: Option<&'a str>,
+        group: Option<&'a str>,
+        ssh_tunnel: Option<&'a SshTunnel>,
+        auto_start: bool,
+        external: bool,
+    }
+
+    let record = Record {
+        name: &info.name,
+        db_type: info.db_type.template_name(),
+        username: &credentials.username,
+        password: Some(credentials.password.as_str()).filter(|_| show_secrets),
+        database: &credentials.database,
+        port: credentials.port,
+        root_password: credentials.root_password.as_deref().filter(|_| show_secrets),
+        connection_string: if show_secrets {
+            info.connection_string.reveal().to_string()
+        } else {
+            info.connection_string.to_string()
+        },
+        created_at: info.created_at,
+        container_id: &info.container_id,
+        image: &info.image,
+        volume: &info.volume,
+        connect_database: credentials.connect_database.as_deref(),
+        connection_template: credentials.connection_template.as_deref(),
+        password_source: credentials.password_source.as_ref().map(ToString::to_string),
+        secret_files: settings.secret_files,
+        shm_size: info.shm_size,
+        tls: info.tls,
+        replica_set: info.replica_set,
+        timezone: info.timezone.as_deref(),
+        memory: info.memory,
+        memory_swap: info.memory_swap,
+        oom_kill_disable: info.oom_kill_disable,
+        network: info.network.as_deref(),
+        group: info.group.as_deref(),
+        ssh_tunnel: info.ssh_tunnel.as_ref(),
+        auto_start: info.auto_start,
+        external: info.external,
+    };
+    println!("{}", serde_json::to_string_pretty(&record)?);
+    Ok(())
+}
+
+/// `db-tool compose <name> [--show-secrets] [--output <file>]`: print a
+/// docker-compose service for the database, or write it to a file. Passwords
+/// are masked unless `--show-secrets` is given.
+pub fn compose(args: &[String]) -> Result<(), anyhow::Error> {
+    let mut name = None;
+    let mut show_secrets = false;
+    let mut output = None;
+    let mut passphrase_file = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--show-secrets" => show_secrets = true,
+            "--output" => output = Some(args.next().ok_or_else(|| anyhow::anyhow!("Missing value for --output"))?),
+            "--passphrase-file" => {
+                let path = args.next().ok_or_else(|| anyhow::anyhow!("Missing value for --passphrase-file"))?;
+                passphrase_file = Some(path.as_str());
+            }
+            flag if flag.starts_with('-') => return Err(anyhow::anyhow!("Unknown option for compose: {}", flag)),
+            value if name.is_none() => name = Some(value.to_string()),
+            value => return Err(anyhow::anyhow!("Unexpected argument: {}", value)),
+        }
+    }
+    let name = name.ok_or_else(|| anyhow::anyhow!("Usage: db-tool compose <name> [--show-secrets] [--output <file>]"))?;
+
+    let (config, passphrase) = authenticate(passphrase_file)?;
+    let service = config.compose_service(&name, &passphrase, show_secrets)?;
+    match output {
+        Some(path) => {
+            std::fs::write(path, service).map_err(|e| anyhow::anyhow!("Failed to write {}: {}", path, e))?;
+            status!("✓ Compose service for '{}' written to {}", name, path);
+        }
+        None => print!("{}", service),
+    }
+    Ok(())
+}
+
+/// `db-tool share <name> <file> [--share-password-file <path>] [--passphrase-file <path>]`:
+/// write one database to a file encrypted with its own share password, for
+/// `import-share` on another machine
+pub fn share(args: &[String]) -> Result<(), anyhow::Error> {
+    let mut positional = Vec::new();
+    let mut share_password_file = None;
+    let mut passphrase_file = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--share-password-file" => {
+                let path = args.next().ok_or_else(|| anyhow::anyhow!("Missing value for --share-password-file"))?;
+                share_password_file = Some(path.as_str());
+            }
+            "--passphrase-file" => {
+                let path = args.next().ok_or_else(|| anyhow::anyhow!("Missing value for --passphrase-file"))?;
+                passphrase_file = Some(path.as_str());
+            }
+            flag if flag.starts_with('-') => return Err(anyhow::anyhow!("Unknown option for share: {}", flag)),
+            value => positional.push(value.to_string()),
+        }
+    }
+    let [name, path] = <[String; 2]>::try_from(positional)
+        .map_err(|_| anyhow::anyhow!("Usage: db-tool share <name> <file> [--share-password-file <path>]"))?;
+    if std::path::Path::new(&path).exists() {
+        return Err(anyhow::anyhow!("'{}' already exists", path));
+    }
+
+    let (config, passphrase) = authenticate(passphrase_file)?;
+    // Fail on a wrong name before asking for a password
+    config.get_database(&name, &passphrase)?;
+    let share_password = read_share_password(share_password_file, true)?;
+    let contents = config.export_share(&name, &passphrase, &share_password)?;
+    std::fs::write(&path, contents).map_err(|e| anyhow::anyhow!("Cannot write '{}': {}", path, e))?;
+    status!("✓ '{}' written to {}. Send the share password separately.", name, path);
+    Ok(())
+}
+
+/// `db-tool import-share <file> [--name <name>] [--share-password-file <path>] [--passphrase-file <path>]`:
+/// add a database from a share file and create its container
+pub fn import_share(args: &[String]) -> Result<(), anyhow::Error> {
+    let mut path = None;
+    let mut name = None;
+    let mut share_password_file = None;
+    let mut passphrase_file = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--name" => {
+                let value = args.next().ok_or_else(|| anyhow::anyhow!("Missing value for --name"))?;
+                name = Some(value.as_str());
+            }
+            "--share-password-file" => {
+                let path = args.next().ok_or_else(|| anyhow::anyhow!("Missing value for --share-password-file"))?;
+                share_password_file = Some(path.as_str());
+            }
+            "--passphrase-file" => {
+                let path = args.next().ok_or_else(|| anyhow::anyhow!("Missing value for --passphrase-file"))?;
+                passphrase_file = Some(path.as_str());
+            }
+            flag if flag.starts_with('-') => return Err(anyhow::anyhow!("Unknown option for import-share: {}", flag)),
+            value if path.is_none() => path = Some(value.to_string()),
+            value => return Err(anyhow::anyhow!("Unexpected argument: {}", value)),
+        }
+    }
+    let path = path.ok_or_else(|| anyhow::anyhow!("Usage: db-tool import-share <file> [--name <name>]"))?;
+    let contents = std::fs::read_to_string(&path).map_err(|e| anyhow::anyhow!("Cannot read '{}': {}", path, e))?;
+
+    let (mut config, passphrase) = authenticate(passphrase_file)?;
+    let share_password = read_share_password(share_password_file, false)?;
+    let name = config.import_share(&contents, &share_password, name, &passphrase)?;
+    status!("✓ Imported '{}'", name);
+
+    let rt = Runtime::new()?;
+    let docker_manager = DockerManager::new()?;
+    rt.block_on(config.recreate_container(&name, &passphrase, &docker_manager))
+        .map_err(|e| anyhow::anyhow!("The container couldn't be created: {}. Create it later with Clean Up Config in the interface", e))?;
+    status!("✓ Container created");
+    Ok(())
+}
+
+/// `db-tool verify [--passphrase-file <path>]`: exit 0 if the passphrase
+/// matches the stored hash. No database entries are decrypted.
+pub fn verify(args: &[String]) -> Result<bool, anyhow::Error> {
+    let mut passphrase_file = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--passphrase-file" => {
+                let path = args.next().ok_or_else(|| anyhow::anyhow!("Missing value for --passphrase-file"))?;
+                passphrase_file = Some(path.as_str());
+            }
+            other => return Err(anyhow::anyhow!("Unknown option for verify: {}", other)),
+        }
+    }
+
+    if !AppConfig::config_exists() {
+        return Err(anyhow::anyhow!("Configuration file not found. Run the app once to initialize."));
+    }
+    let passphrase = read_passphrase(passphrase_file)?;
+    Ok(AppConfig::verify_only(&passphrase))
+}
+
+/// `db-tool metrics [--output <file>] [--passphrase-file <path>]`: print
+/// Prometheus metrics for every database, or write them to a file for the
+/// node exporter's textfile collector
+pub fn metrics(args: &[String]) -> Result<(), anyhow::Error> {
+    let mut output = None;
+    let mut passphrase_file = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--output" => output = Some(args.next().ok_or_else(|| anyhow::anyhow!("Missing value for --output"))?),
+            "--passphrase-file" => {
+                let path = args.next().ok_or_else(|| anyhow::anyhow!("Missing value for --passphrase-file"))?;
+                passphrase_file = Some(path.as_str());
+            }
+            other => return Err(anyhow::anyhow!("Unknown option for metrics: {}", other)),
+        }
+    }
+
+    let (config, passphrase) = authenticate(passphrase_file)?;
+    let docker_manager = DockerManager::new()?;
+    let samples = Runtime::new()?.block_on(metrics::collect(&config, &passphrase, &docker_manager))?;
+    let text = metrics::render(&samples);
+    match output {
+        // The collector may read at any time, so it must never see a partial file
+        Some(path) => {
+            let temp_path = format!("{}.tmp", path);
+            std::fs::write(&temp_path, text)
+                .and_then(|()| std::fs::rename(&temp_path, path))
+                .map_err(|e| anyhow::anyhow!("Failed to write {}: {}", path, e))?;
+            status!("✓ Metrics for {} databases written to {}", samples.len(), path);
+        }
+        None => print!("{}", text),
+    }
+    Ok(())
+}
+
+/// `db-tool tunnel <name> [--via <[user@]host[:port]> [--remote <host[:port]>] | --off]`:
+/// reach a database through an SSH forward, or directly again. Without options
+/// the tunnel and the ssh command that opens it are printed.
+pub fn tunnel(args: &[String]) -> Result<(), anyhow::Error> {
+    let mut name = None;
+    let mut via = None;
+    let mut remote = None;
+    let mut off = false;
+    let mut passphrase_file = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--via" => via = Some(args.next().ok_or_else(|| anyhow::anyhow!("Missing value for --via"))?.as_str()),
+            "--remote" => remote = Some(args.next().ok_or_else(|| anyhow::anyhow!("Missing value for --remote"))?.as_str()),
+            "--off" => off = true,
+            "--passphrase-file" => {
+                let path = args.next().ok_or_else(|| anyhow::anyhow!("Missing value for --passphrase-file"))?;
+                passphrase_file = Some(path.as_str());
+            }
+            flag if flag.starts_with('-') => return Err(anyhow::anyhow!("Unknown option for tunnel: {}", flag)),
+            value if name.is_none() => name = Some(value.to_string()),
+            value => return Err(anyhow::anyhow!("Unexpected argument: {}", value)),
+        }
+    }
+    let name = name.ok_or_else(|| anyhow::anyhow!("Usage: db-tool tunnel <name> [--via <[user@]host[:port]> [--remote <host[:port]>] | --off]"))?;
+    if off && via.is_some() {
+        return Err(anyhow::anyhow!("--off and --via can't be combined"));
+    }
+    if remote.is_some() && via.is_none() {
+        return Err(anyhow::anyhow!("--remote needs --via"));
+    }
+
+    let (mut config, passphrase) = authenticate(passphrase_file)?;
+    let info = config.get_database(&name, &passphrase)?;
+    let port = info.credentials.port;
+    if off {
+        config.set_ssh_tunnel(&name, None)?;
+        status!("✓ '{}' is reached directly again", name);
+    } else if let Some(via) = via {
+        let tunnel = SshTunnel::parse(via, remote)?;
+        config.set_ssh_tunnel(&name, Some(tunnel.clone()))?;
+        status!("✓ '{}' is reached through {}", name, tunnel.describe(port));
+    } else {
+        match &info.ssh_tunnel {
+            Some(tunnel) => {
+                status!("{}", tunnel.describe(port));
+                println!("{}", tunnel.command_line(port));
+            }
+            None => status!("'{}' has no SSH tunnel", name),
+        }
+    }
+    Ok(())
+}
+
+/// `db-tool register <name> [<url>] [--passphrase-file <path>]`: add a
+/// database that runs elsewhere from its `postgresql://`, `mysql://` or
+/// `redis://` URL, without creating a container. Without a URL it is read
+/// from `DATABASE_URL`, which keeps the password out of the shell history.
+pub fn register(args: &[String]) -> Result<(), anyhow::Error> {
+    let mut name = None;
+    let mut url = None;
+    let mut passphrase_file = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--passphrase-file" => {
+                let path = args.next().ok_or_else(|| anyhow::anyhow!("Missing value for --passphrase-file"))?;
+                passphrase_file = Some(path.as_str());
+            }
+            flag if flag.starts_with('-') => return Err(anyhow::anyhow!("Unknown option for register: {}", flag)),
+            value if name.is_none() => name = Some(value.to_string()),
+            value if url.is_none() => url = Some(value.to_string()),
+            value => return Err(anyhow::anyhow!("Unexpected argument: {}", value)),
+        }
+    }
+    let name = name.ok_or_else(|| anyhow::anyhow!("Usage: db-tool register <name> [<url>]  (the URL defaults to $DATABASE_URL)"))?;
+    let url = match url {
+        Some(url) => url,
+        None => std::env::var("DATABASE_URL").map_err(|_| anyhow::anyhow!("No URL given and DATABASE_URL is not set"))?,
+    };
+
+    let (mut config, passphrase) = authenticate(passphrase_file)?;
+    let connection_string = config.register_external(&name, &url, &passphrase)?;
+    status!("✓ External database '{}' registered: {}", name, connection_string);
+    Ok(())
+}
+
+/// `db-tool password-source <name> [<env:VAR|file:path> | --store]`: read a
+/// database's password from an environment variable or secret file whenever
+/// it is used instead of storing it, or store it again. Without options the
+/// current source is printed.
+pub fn password_source(args: &[String]) -> Result<(), anyhow::Error> {
+    let mut name = None;
+    let mut source = None;
+    let mut store = false;
+    let mut passphrase_file = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--store" => store = true,
+            "--passphrase-file" => {
+                let path = args.next().ok_or_else(|| anyhow::anyhow!("Missing value for --passphrase-file"))?;
+                passphrase_file = Some(path.as_str());
+            }
+            flag if flag.starts_with('-') => return Err(anyhow::anyhow!("Unknown option for password-source: {}", flag)),
+            value if name.is_none() => name = Some(value.to_string()),
+            value if source.is_none() => source = Some(PasswordSource::parse(value)?),
+            value => return Err(anyhow::anyhow!("Unexpected argument: {}", value)),
+        }
+    }
+    let name = name.ok_or_else(|| anyhow::anyhow!("Usage: db-tool password-source <name> [<env:VAR|file:path> | --store]"))?;
+    if store && source.is_some() {
+        return Err(anyhow::anyhow!("--store and a source can't be combined"));
+    }
+
+    let (mut config, passphrase) = authenticate(passphrase_file)?;
+    if store {
+        config.set_password_source(&name, None, &passphrase)?;
+        status!("✓ The password of '{}' is stored (encrypted) in the config again", name);
+    } else if let Some(source) = source {
+        config.set_password_source(&name, Some(source.clone()), &passphrase)?;
+        status!("✓ The password of '{}' is read from {} and no longer stored", name, source);
+    } else {
+        match config.get_database(&name, &passphrase)?.credentials.password_source {
+            Some(source) => println!("{}", source),
+            None => status!("The password of '{}' is stored in the config", name),
+        }
+    }
+    Ok(())
+}
+
+/// `db-tool full-encryption <on|off> [--passphrase-file <path>]`: encrypt
+/// the database names, types, container IDs and dates too, or store them in
+/// plaintext again
+pub fn full_encryption(args: &[String]) -> Result<(), anyhow::Error> {
+    let mut enabled = None;
+    let mut passphrase_file = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "on" if enabled.is_none() => enabled = Some(true),
+            "off" if enabled.is_none() => enabled = Some(false),
+            "--passphrase-file" => {
+                let path = args.next().ok_or_else(|| anyhow::anyhow!("Missing value for --passphrase-file"))?;
+                passphrase_file = Some(path.as_str());
+            }
+            other => return Err(anyhow::anyhow!("Unknown argument for full-encryption: {}", other)),
+        }
+    }
+    let enabled = enabled.ok_or_else(|| anyhow::anyhow!("Usage: db-tool full-encryption <on|off>"))?;
+
+    let (mut config, passphrase) = authenticate(passphrase_file)?;
+    if config.is_fully_encrypted() == enabled {
+        status!("Full encryption is already {}", if enabled { "on" } else { "off" });
+        return Ok(());
+    }
+    config.set_full_encryption(enabled, &passphrase)?;
+    if enabled {
+        status!("✓ Full encryption on: database names and container IDs now need the passphrase too");
+    } else {
+        status!("✓ Full encryption off: only credentials and connection strings are encrypted");
+    }
+    Ok(())
+}
+
+/// `db-tool exec <name> [--passphrase-file <path>] -- <cmd>...`: run a command
+/// in the database's container, streaming its output. Returns the command's
+/// exit code so it can be passed on.
+pub fn exec(args: &[String]) -> Result<i32, anyhow::Error> {
+    let usage = || anyhow::anyhow!("Usage: db-tool exec <name> -- <command> [args...]");
+    let separator = args.iter().position(|arg| arg == "--").ok_or_else(usage)?;
+    let (options, cmd) = (&args[..separator], &args[separator + 1..]);
+    if cmd.is_empty() {
+        return Err(usage());
+    }
+
+    let mut name = None;
+    let mut passphrase_file = None;
+    let mut options = options.iter();
+    while let Some(arg) = options.next() {
+        match arg.as_str() {
+            "--passphrase-file" => {
+                let path = options.next().ok_or_else(|| anyhow::anyhow!("Missing value for --passphrase-file"))?;
+                passphrase_file = Some(path.as_str());
+            }
+            flag if flag.starts_with('-') => return Err(anyhow::anyhow!("Unknown option for exec: {}", flag)),
+            value if name.is_none() => name = Some(value.to_string()),
+            value => return Err(anyhow::anyhow!("Unexpected argument: {}", value)),
+        }
+    }
+    let name = name.ok_or_else(usage)?;
+
+    let (config, passphrase) = authenticate(passphrase_file)?;
+    let info = config.get_database(&name, &passphrase)?;
+
+    let rt = Runtime::new()?;
+    let docker_manager = DockerManager::new()?;
+    let cmd: Vec<&str> = cmd.iter().map(String::as_str).collect();
+    let exit_code = rt.block_on(docker_manager.exec_streaming(&info.container_id, &cmd, &mut |chunk| {
+        // Output is best effort: a closed pipe (e.g. `| head`) shouldn't abort the command
+        let _ = match chunk {
+            LogChunk::Stdout(bytes) => io::stdout().write_all(&bytes).and_then(|()| io::stdout().flush()),
+            LogChunk::Stderr(bytes) => io::stderr().write_all(&bytes),
+        };
+    }))?;
+    Ok(i32::try_from(exit_code).unwrap_or(1))
+}
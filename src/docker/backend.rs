@@ -0,0 +1,599 @@
+use super::registry::{registry_auth, registry_host};
+use super::{classify_error, docker_error};
+use async_trait::async_trait;
+use futures_util::StreamExt;
+use hyper::{Body, Client, Request};
+use shiplift::tty::TtyChunk;
+use shiplift::{
+    ContainerOptions, Docker, Exec, ExecContainerOptions, LogsOptions, PullOptions,
+    RmContainerOptions,
+};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+/// What to create a container from
+#[derive(Debug, Clone, Default)]
+pub struct ContainerSpec {
+    pub name: Option<String>,       // None lets Docker pick a name
+    pub image: String,
+    pub env: Vec<String>,           // KEY=value
+    pub ports: Vec<(u32, u32)>,     // (container port, host port), tcp
+    pub volumes: Vec<String>,       // source:target[:mode]
+    pub cmd: Vec<String>,           // empty keeps the image's command
+    pub entrypoint: Vec<String>,    // empty keeps the image's entrypoint
+    pub tmpfs: Vec<String>,         // container paths mounted as in-memory filesystems
+    pub shm_size: Option<u64>,      // bytes for /dev/shm; None keeps Docker's 64MB
+    pub memory: Option<u64>,        // memory limit in bytes; None is unlimited
+    pub memory_swap: Option<i64>,   // memory plus swap in bytes, -1 unlimited; None is twice `memory`
+    pub oom_kill_disable: bool,     // keep the process alive when it hits `memory`
+    pub labels: HashMap<String, String>,
+    pub network: Option<String>,    // user-defined network to join; None uses the default bridge
+}
+
+/// A container as listed by the daemon
+#[derive(Debug, Clone)]
+pub struct ContainerSummary {
+    pub id: String,
+    pub name: String,
+    pub image: String,
+    pub ports: Vec<(u16, u16)>, // published (container port, host port) pairs
+    pub health: Option<String>, // starting, healthy or unhealthy; None without a healthcheck
+    pub labels: HashMap<String, String>,
+}
+
+/// What `docker inspect` reports about a container's process
+#[derive(Debug, Clone)]
+pub struct ContainerState {
+    pub running: bool,
+    pub status: String, // created, running, exited, dead, ...
+    pub exit_code: u64,
+    pub oom_killed: bool,
+    pub health: Option<String>,        // starting, healthy or unhealthy; None without a healthcheck
+    pub health_output: Option<String>, // output of the last health probe
+}
+
+/// Resource usage of a running container, as `docker stats` shows it
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ContainerStats {
+    pub memory_usage: u64, // bytes, without the page cache
+    pub memory_limit: u64, // bytes; the host's memory without a limit
+    pub cpu_seconds: f64,  // CPU time used since the container started
+}
+
+/// A piece of container output
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LogChunk {
+    Stdout(Vec<u8>),
+    Stderr(Vec<u8>),
+}
+
+/// The raw Docker operations `DockerManager` is built on. `ShipliftBackend`
+/// talks to the daemon; tests use an in-memory implementation.
+#[async_trait]
+pub trait DockerBackend: Send + Sync {
+    async fn ping(&self) -> Result<(), anyhow::Error>;
+    /// Product and version of the engine, to tell Docker and Podman apart
+    async fn engine_version(&self) -> Result<String, anyhow::Error>;
+    async fn image_exists(&self, image: &str) -> Result<bool, anyhow::Error>;
+    /// The entrypoint and command an image runs unless a container overrides them
+    async fn image_command(&self, image: &str) -> Result<(Vec<String>, Vec<String>), anyhow::Error>;
+    async fn pull_image(&self, image: &str) -> Result<(), anyhow::Error>;
+    async fn create_container(&self, spec: &ContainerSpec) -> Result<String, anyhow::Error>;
+    async fn start_container(&self, id: &str) -> Result<(), anyhow::Error>;
+    async fn stop_container(&self, id: &str, timeout_secs: u64) -> Result<(), anyhow::Error>;
+    /// Wait for the container to exit and return its exit code
+    async fn wait_container(&self, id: &str) -> Result<u64, anyhow::Error>;
+    async fn rename_container(&self, id: &str, new_name: &str) -> Result<(), anyhow::Error>;
+    async fn remove_container(&self, id: &str, remove_volumes: bool) -> Result<(), anyhow::Error>;
+    async fn list_container_ids(&self, include_stopped: bool) -> Result<HashSet<String>, anyhow::Error>;
+    async fn list_running_containers(&self) -> Result<Vec<ContainerSummary>, anyhow::Error>;
+    /// (host source, container destination) of each mount
+    async fn container_mounts(&self, id: &str) -> Result<Vec<(String, String)>, anyhow::Error>;
+    /// The `KEY=value` environment the container was created with
+    async fn container_env(&self, id: &str) -> Result<Vec<String>, anyhow::Error>;
+    async fn container_labels(&self, id: &str) -> Result<HashMap<String, String>, anyhow::Error>;
+    async fn inspect_state(&self, id: &str) -> Result<ContainerState, anyhow::Error>;
+    /// The full ID of a container given by name or ID
+    async fn resolve_container(&self, name_or_id: &str) -> Result<String, anyhow::Error>;
+    async fn container_stats(&self, id: &str) -> Result<ContainerStats, anyhow::Error>;
+    async fn list_volumes(&self) -> Result<Vec<String>, anyhow::Error>;
+    async fn remove_volume(&self, name: &str) -> Result<(), anyhow::Error>;
+    async fn list_networks(&self) -> Result<Vec<String>, anyhow::Error>;
+    async fn create_network(&self, name: &str, labels: &HashMap<String, String>) -> Result<(), anyhow::Error>;
+    async fn remove_network(&self, name: &str) -> Result<(), anyhow::Error>;
+    /// Container output so far; `tail` limits it to the last lines
+    async fn logs(&self, id: &str, tail: Option<usize>) -> Result<Vec<LogChunk>, anyhow::Error>;
+    /// Pass new output to `on_chunk` as it is written, until the container
+    /// stops or `on_chunk` returns false
+    async fn follow_logs(
+        &self,
+        id: &str,
+        on_chunk: &mut (dyn FnMut(LogChunk) -> bool + Send),
+    ) -> Result<(), anyhow::Error>;
+    /// Run a command in a running container, with `env` (`KEY=value`) added to
+    /// its environment, passing its output to `on_chunk` as it arrives, and
+    /// return the exit code
+    async fn exec(
+        &self,
+        id: &str,
+        cmd: &[&str],
+        env: &[&str],
+        on_chunk: &mut (dyn FnMut(LogChunk) + Send),
+    ) -> Result<u64, anyhow::Error>;
+    async fn copy_file_into(&self, id: &str, path: &Path, bytes: &[u8]) -> Result<(), anyhow::Error>;
+}
+
+pub struct ShipliftBackend {
+    docker: Docker,
+}
+
+impl ShipliftBackend {
+    pub fn new() -> Self {
+        Self { docker: Docker::new() }
+    }
+}
+
+fn log_chunk(chunk: TtyChunk) -> Option<LogChunk> {
+    match chunk {
+        TtyChunk::StdOut(bytes) => Some(LogChunk::Stdout(bytes)),
+        TtyChunk::StdErr(bytes) => Some(LogChunk::Stderr(bytes)),
+        TtyChunk::StdIn(_) => None,
+    }
+}
+
+/// shiplift's builder can't set `HostConfig.ShmSize`, `OomKillDisable` or
+/// `Tmpfs`, or an entrypoint of several words, so containers that need them
+/// are created with a direct Engine API call.
+async fn create_with_host_config(opts: &ContainerOptions, spec: &ContainerSpec) -> Result<String, anyhow::Error> {
+    let mut body: serde_json::Value = serde_json::from_str(&opts.serialize().map_err(docker_error)?)?;
+    if let Some(shm_size) = spec.shm_size {
+        body["HostConfig"]["ShmSize"] = serde_json::json!(shm_size);
+    }
+    if spec.oom_kill_disable {
+        body["HostConfig"]["OomKillDisable"] = serde_json::json!(true);
+    }
+    // On a user-defined network the other members reach the container by its name
+    if let Some(network) = &spec.network {
+        body["HostConfig"]["NetworkMode"] = serde_json::json!(network);
+    }
+    if !spec.tmpfs.is_empty() {
+        let mounts: serde_json::Map<String, serde_json::Value> = spec.tmpfs.iter().map(|path| (path.clone(), serde_json::json!(""))).collect();
+        body["HostConfig"]["Tmpfs"] = serde_json::Value::Object(mounts);
+    }
+    if !spec.entrypoint.is_empty() {
+        body["Entrypoint"] = serde_json::json!(spec.entrypoint);
+    }
+    let path = match &opts.name {
+        Some(name) => format!("/containers/create?name={}", name),
+        None => "/containers/create".to_string(),
+    };
+    let request = Request::post("/")
+        .header("Content-Type", "application/json")
+        .body(Body::from(body.to_string()))?;
+    let reply = engine_request(request, &path).await?;
+    reply["Id"]
+        .as_str()
+        .map(String::from)
+        .ok_or_else(|| anyhow::anyhow!("Unexpected reply from Docker: {}", reply))
+}
+
+// Replies that shiplift would deserialize into fixed structs are read as
+// plain JSON: shiplift's `State` has no `Health`, and Podman's Docker-compatible
+// API leaves out or nulls fields (`Labels`, `SizeRw`, ...) that shiplift requires
+async fn get_json(path: &str) -> Result<serde_json::Value, anyhow::Error> {
+    engine_request(Request::get("/").body(Body::empty())?, path).await
+}
+
+pub(super) fn state_from_inspect(state: &serde_json::Value) -> ContainerState {
+    // Podman before 4.0 reports the healthcheck as `Healthcheck`
+    let health = if state["Health"].is_object() { &state["Health"] } else { &state["Healthcheck"] };
+    ContainerState {
+        running: state["Running"].as_bool().unwrap_or(false),
+        status: state["Status"].as_str().unwrap_or("unknown").to_string(),
+        exit_code: state["ExitCode"].as_u64().unwrap_or(0),
+        oom_killed: state["OOMKilled"].as_bool().unwrap_or(false),
+        // "none" is what Docker reports when the healthcheck is disabled
+        health: health["Status"].as_str().filter(|status| *status != "none").map(String::from),
+        health_output: health["Log"]
+            .as_array()
+            .and_then(|log| log.last())
+            .and_then(|probe| probe["Output"].as_str())
+            .map(|output| output.trim().to_string()),
+    }
+}
+
+pub(super) fn stats_from_json(stats: &serde_json::Value) -> ContainerStats {
+    let memory = &stats["memory_stats"];
+    // Like `docker stats`, the page cache doesn't count: `inactive_file` on
+    // cgroup v2, `cache` on v1
+    let cache = memory["stats"]["inactive_file"].as_u64().or_else(|| memory["stats"]["cache"].as_u64()).unwrap_or(0);
+    ContainerStats {
+        memory_usage: memory["usage"].as_u64().unwrap_or(0).saturating_sub(cache),
+        memory_limit: memory["limit"].as_u64().unwrap_or(0),
+        cpu_seconds: stats["cpu_stats"]["cpu_usage"]["total_usage"].as_u64().unwrap_or(0) as f64 / 1e9,
+    }
+}
+
+/// Health from a container list status like "Up 5 minutes (health: starting)"
+pub(super) fn health_from_status(status: &str) -> Option<String> {
+    let detail = status.rsplit_once('(')?.1.strip_suffix(')')?;
+    match detail.strip_prefix("health: ").unwrap_or(detail) {
+        health @ ("starting" | "healthy" | "unhealthy") => Some(health.to_string()),
+        _ => None,
+    }
+}
+
+/// String labels of a list entry or an inspect `Config`; Podman sends null for none
+pub(super) fn labels_of(value: &serde_json::Value) -> HashMap<String, String> {
+    value["Labels"]
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter_map(|(key, value)| Some((key.clone(), value.as_str()?.to_string())))
+        .collect()
+}
+
+/// (container port, host port) of the published tcp ports in a list entry
+pub(super) fn published_tcp_ports(container: &serde_json::Value) -> Vec<(u16, u16)> {
+    container["Ports"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|port| port["Type"].as_str().unwrap_or("tcp") == "tcp")
+        .filter_map(|port| {
+            let private = u16::try_from(port["PrivatePort"].as_u64()?).ok()?;
+            let public = u16::try_from(port["PublicPort"].as_u64()?).ok()?;
+            Some((private, public))
+        })
+        .collect()
+}
+
+/// Product and version of the engine behind `DOCKER_HOST`, e.g. "Podman Engine 4.9.3"
+pub(super) fn engine_name(version: &serde_json::Value) -> String {
+    let component = version["Components"].as_array().and_then(|components| components.first());
+    match component {
+        Some(component) => format!(
+            "{} {}",
+            component["Name"].as_str().unwrap_or("Engine"),
+            component["Version"].as_str().or(version["Version"].as_str()).unwrap_or("unknown")
+        ),
+        None => format!("Docker Engine {}", version["Version"].as_str().unwrap_or("unknown")),
+    }
+}
+
+/// Send a request to the Engine API on the same `DOCKER_HOST` shiplift uses
+/// and return the JSON reply
+async fn engine_request(request: Request<Body>, path: &str) -> Result<serde_json::Value, anyhow::Error> {
+    let host = super::docker_host();
+    let (mut parts, body) = request.into_parts();
+    let response = match host.strip_prefix("unix://") {
+        Some(socket) => {
+            parts.uri = hyperlocal::Uri::new(socket, path).into();
+            Client::builder()
+                .build::<_, Body>(hyperlocal::UnixConnector)
+                .request(Request::from_parts(parts, body))
+                .await
+        }
+        None => {
+            let base = host.replacen("tcp://", "http://", 1);
+            parts.uri = format!("{}{}", base.trim_end_matches('/'), path).parse()?;
+            Client::new().request(Request::from_parts(parts, body)).await
+        }
+    }
+    .map_err(http_error)?;
+
+    let status = response.status();
+    let bytes = hyper::body::to_bytes(response.into_body()).await.map_err(http_error)?;
+    let reply: serde_json::Value = serde_json::from_slice(&bytes).unwrap_or_default();
+    if !status.is_success() {
+        let message = reply["message"].as_str().unwrap_or_else(|| status.canonical_reason().unwrap_or("request failed"));
+        return Err(classify_error(format!("Docker Error: {} ({})", message.trim(), status.as_u16()), None));
+    }
+    Ok(reply)
+}
+
+// Same classification as shiplift errors: connection problems carry an io::Error
+fn http_error(err: hyper::Error) -> anyhow::Error {
+    let mut io_kind = None;
+    let mut source: Option<&(dyn std::error::Error + 'static)> = Some(&err);
+    while let Some(current) = source {
+        if let Some(io_err) = current.downcast_ref::<std::io::Error>() {
+            io_kind = Some(io_err.kind());
+            break;
+        }
+        source = current.source();
+    }
+    classify_error(err.to_string(), io_kind)
+}
+
+#[async_trait]
+impl DockerBackend for ShipliftBackend {
+    async fn ping(&self) -> Result<(), anyhow::Error> {
+        self.docker.ping().await.map_err(docker_error)?;
+        Ok(())
+    }
+
+    async fn engine_version(&self) -> Result<String, anyhow::Error> {
+        Ok(engine_name(&get_json("/version").await?))
+    }
+
+    async fn image_exists(&self, image: &str) -> Result<bool, anyhow::Error> {
+        Ok(self.docker.images().get(image).inspect().await.is_ok())
+    }
+
+    async fn image_command(&self, image: &str) -> Result<(Vec<String>, Vec<String>), anyhow::Error> {
+        let inspect = get_json(&format!("/images/{}/json", image)).await?;
+        // Either may be null when the image leaves it unset
+        let words = |value: &serde_json::Value| -> Vec<String> {
+            value.as_array().map(|words| words.iter().filter_map(|word| word.as_str().map(String::from)).collect()).unwrap_or_default()
+        };
+        Ok((words(&inspect["Config"]["Entrypoint"]), words(&inspect["Config"]["Cmd"])))
+    }
+
+    async fn pull_image(&self, image: &str) -> Result<(), anyhow::Error> {
+        // Private registries need the credentials `docker login` stored
+        let mut options = PullOptions::builder();
+        options.image(image);
+        if let Some(auth) = registry_auth(image) {
+            tracing::debug!("Pulling {} with stored credentials for {}", image, registry_host(image));
+            options.auth(auth);
+        }
+        let mut stream = self.docker.images().pull(&options.build());
+
+        while let Some(pull_result) = stream.next().await {
+            match pull_result {
+                Ok(output) => {
+                    // Registry failures arrive as an "error" entry in the progress stream
+                    if let Some(error) = output.get("error").and_then(|e| e.as_str()) {
+                        return Err(classify_error(format!("Failed to pull {}: {}", image, error), None));
+                    }
+                    if let Some(status) = output.get("status") {
+                        if let Some(status_str) = status.as_str() {
+                            tracing::debug!("Pull {}: {}", image, status_str);
+                        }
+                    }
+                }
+                Err(e) => return Err(docker_error(e)),
+            }
+        }
+        Ok(())
+    }
+
+    async fn create_container(&self, spec: &ContainerSpec) -> Result<String, anyhow::Error> {
+        let mut opts = ContainerOptions::builder(&spec.image);
+        if let Some(name) = &spec.name {
+            opts.name(name);
+        }
+        if !spec.env.is_empty() {
+            opts.env(spec.env.iter().map(|s| s.as_str()).collect::<Vec<_>>());
+        }
+        for (container_port, host_port) in &spec.ports {
+            opts.expose(*container_port, "tcp", *host_port);
+        }
+        if !spec.volumes.is_empty() {
+            opts.volumes(spec.volumes.iter().map(|s| s.as_str()).collect());
+        }
+        if !spec.cmd.is_empty() {
+            opts.cmd(spec.cmd.iter().map(|s| s.as_str()).collect());
+        }
+        if !spec.labels.is_empty() {
+            opts.labels(&spec.labels.iter().map(|(key, value)| (key.as_str(), value.as_str())).collect());
+        }
+
+        if let Some(memory) = spec.memory {
+            opts.memory(memory);
+        }
+        if let Some(memory_swap) = spec.memory_swap {
+            opts.memory_swap(memory_swap);
+        }
+
+        if spec.shm_size.is_some() || spec.oom_kill_disable || spec.network.is_some() || !spec.tmpfs.is_empty() || !spec.entrypoint.is_empty() {
+            return create_with_host_config(&opts.build(), spec).await;
+        }
+        let container = self.docker.containers().create(&opts.build()).await.map_err(docker_error)?;
+        Ok(container.id)
+    }
+
+    async fn start_container(&self, id: &str) -> Result<(), anyhow::Error> {
+        self.docker.containers().get(id).start().await.map_err(docker_error)
+    }
+
+    async fn stop_container(&self, id: &str, timeout_secs: u64) -> Result<(), anyhow::Error> {
+        self.docker
+            .containers()
+            .get(id)
+            .stop(Some(std::time::Duration::from_secs(timeout_secs)))
+            .await
+            .map_err(docker_error)
+    }
+
+    async fn wait_container(&self, id: &str) -> Result<u64, anyhow::Error> {
+        let exit = self.docker.containers().get(id).wait().await.map_err(docker_error)?;
+        Ok(exit.status_code)
+    }
+
+    async fn rename_container(&self, id: &str, new_name: &str) -> Result<(), anyhow::Error> {
+        self.docker.containers().get(id).rename(new_name).await.map_err(docker_error)
+    }
+
+    async fn remove_container(&self, id: &str, remove_volumes: bool) -> Result<(), anyhow::Error> {
+        let opts = RmContainerOptions::builder()
+            .force(true)
+            .volumes(remove_volumes)
+            .build();
+        self.docker.containers().get(id).remove(opts).await.map_err(docker_error)
+    }
+
+    async fn list_container_ids(&self, include_stopped: bool) -> Result<HashSet<String>, anyhow::Error> {
+        let path = if include_stopped { "/containers/json?all=true" } else { "/containers/json" };
+        let containers = get_json(path).await?;
+        Ok(containers
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|container| container["Id"].as_str().map(String::from))
+            .collect())
+    }
+
+    async fn list_running_containers(&self) -> Result<Vec<ContainerSummary>, anyhow::Error> {
+        let containers = get_json("/containers/json").await?;
+        Ok(containers
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|container| {
+                Some(ContainerSummary {
+                    id: container["Id"].as_str()?.to_string(),
+                    name: container["Names"][0].as_str().unwrap_or_default().trim_start_matches('/').to_string(),
+                    image: container["Image"].as_str().unwrap_or_default().to_string(),
+                    ports: published_tcp_ports(container),
+                    health: container["Status"].as_str().and_then(health_from_status),
+                    labels: labels_of(container),
+                })
+            })
+            .collect())
+    }
+
+    async fn container_mounts(&self, id: &str) -> Result<Vec<(String, String)>, anyhow::Error> {
+        let details = get_json(&format!("/containers/{}/json", id)).await?;
+        Ok(details["Mounts"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|mount| Some((mount["Source"].as_str()?.to_string(), mount["Destination"].as_str()?.to_string())))
+            .collect())
+    }
+
+    async fn container_env(&self, id: &str) -> Result<Vec<String>, anyhow::Error> {
+        let details = get_json(&format!("/containers/{}/json", id)).await?;
+        Ok(details["Config"]["Env"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.as_str().map(String::from))
+            .collect())
+    }
+
+    async fn container_labels(&self, id: &str) -> Result<HashMap<String, String>, anyhow::Error> {
+        let details = get_json(&format!("/containers/{}/json", id)).await?;
+        Ok(labels_of(&details["Config"]))
+    }
+
+    async fn inspect_state(&self, id: &str) -> Result<ContainerState, anyhow::Error> {
+        let details = get_json(&format!("/containers/{}/json", id)).await?;
+        Ok(state_from_inspect(&details["State"]))
+    }
+
+    async fn resolve_container(&self, name_or_id: &str) -> Result<String, anyhow::Error> {
+        let details = get_json(&format!("/containers/{}/json", name_or_id)).await?;
+        details["Id"]
+            .as_str()
+            .map(String::from)
+            .ok_or_else(|| anyhow::anyhow!("The engine reported no ID for container {}", name_or_id))
+    }
+
+    async fn container_stats(&self, id: &str) -> Result<ContainerStats, anyhow::Error> {
+        // One sample instead of a stream
+        let stats = get_json(&format!("/containers/{}/stats?stream=false", id)).await?;
+        Ok(stats_from_json(&stats))
+    }
+
+    async fn list_volumes(&self) -> Result<Vec<String>, anyhow::Error> {
+        let volumes = self.docker.volumes().list().await.map_err(docker_error)?;
+        Ok(volumes.into_iter().map(|volume| volume.name).collect())
+    }
+
+    async fn remove_volume(&self, name: &str) -> Result<(), anyhow::Error> {
+        self.docker.volumes().get(name).delete().await.map_err(docker_error)
+    }
+
+    async fn list_networks(&self) -> Result<Vec<String>, anyhow::Error> {
+        let networks = get_json("/networks").await?;
+        Ok(networks
+            .as_array()
+            .map(|networks| networks.iter().filter_map(|network| network["Name"].as_str().map(String::from)).collect())
+            .unwrap_or_default())
+    }
+
+    async fn create_network(&self, name: &str, labels: &HashMap<String, String>) -> Result<(), anyhow::Error> {
+        let body = serde_json::json!({ "Name": name, "Driver": "bridge", "Labels": labels, "CheckDuplicate": true });
+        let request = Request::post("/")
+            .header("Content-Type", "application/json")
+            .body(Body::from(body.to_string()))?;
+        engine_request(request, "/networks/create").await.map(|_| ())
+    }
+
+    async fn remove_network(&self, name: &str) -> Result<(), anyhow::Error> {
+        engine_request(Request::delete("/").body(Body::empty())?, &format!("/networks/{}", name)).await.map(|_| ())
+    }
+
+    async fn logs(&self, id: &str, tail: Option<usize>) -> Result<Vec<LogChunk>, anyhow::Error> {
+        let mut opts = LogsOptions::builder();
+        opts.stdout(true).stderr(true);
+        let tail = tail.map(|tail| tail.to_string());
+        if let Some(tail) = &tail {
+            opts.tail(tail);
+        }
+        let container = self.docker.containers().get(id);
+        let mut stream = container.logs(&opts.build());
+
+        let mut chunks = Vec::new();
+        while let Some(chunk) = stream.next().await {
+            chunks.extend(log_chunk(chunk.map_err(docker_error)?));
+        }
+        Ok(chunks)
+    }
+
+    async fn follow_logs(
+        &self,
+        id: &str,
+        on_chunk: &mut (dyn FnMut(LogChunk) -> bool + Send),
+    ) -> Result<(), anyhow::Error> {
+        let opts = LogsOptions::builder()
+            .stdout(true)
+            .stderr(true)
+            .follow(true)
+            .tail("0")
+            .build();
+        let container = self.docker.containers().get(id);
+        let mut stream = container.logs(&opts);
+
+        while let Some(chunk) = stream.next().await {
+            if let Some(chunk) = log_chunk(chunk.map_err(docker_error)?) {
+                if !on_chunk(chunk) {
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn exec(
+        &self,
+        id: &str,
+        cmd: &[&str],
+        env: &[&str],
+        on_chunk: &mut (dyn FnMut(LogChunk) + Send),
+    ) -> Result<u64, anyhow::Error> {
+        let opts = ExecContainerOptions::builder()
+            .cmd(cmd.to_vec())
+            .env(env.to_vec())
+            .attach_stdout(true)
+            .attach_stderr(true)
+            .build();
+        let exec = Exec::create(&self.docker, id, &opts).await.map_err(docker_error)?;
+
+        let mut stream = exec.start();
+        while let Some(chunk) = stream.next().await {
+            if let Some(chunk) = log_chunk(chunk.map_err(docker_error)?) {
+                on_chunk(chunk);
+            }
+        }
+
+        Ok(exec.inspect().await.map_err(docker_error)?.exit_code.unwrap_or(0))
+    }
+
+    async fn copy_file_into(&self, id: &str, path: &Path, bytes: &[u8]) -> Result<(), anyhow::Error> {
+        self.docker.containers().get(id).copy_file_into(path, bytes).await.map_err(docker_error)
+    }
+}
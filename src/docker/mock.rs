@@ -0,0 +1,330 @@
+use super::backend::{ContainerState, ContainerStats, ContainerSummary};
+use super::{ContainerSpec, DockerBackend, LogChunk};
+use async_trait::async_trait;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// A container as the mock daemon knows it
+#[derive(Debug, Clone)]
+pub struct MockContainer {
+    pub spec: ContainerSpec,
+    pub running: bool,
+    pub exit_code: Option<u64>, // set once the process has exited
+    pub start_calls: usize,
+    pub files: HashMap<PathBuf, Vec<u8>>,
+    pub logs: Vec<LogChunk>,
+    pub health: Option<String>, // as Docker reports it for images with a healthcheck
+    pub stats: ContainerStats,
+}
+
+#[derive(Default)]
+pub struct MockState {
+    pub pulled: Vec<String>,
+    pub containers: HashMap<String, MockContainer>,
+    pub volumes: HashSet<String>,
+    pub networks: HashSet<String>,
+    pub fail_start: bool, // make every start_container call fail
+    pub fail_remove: HashSet<String>, // container ids whose removal fails
+    pub exec_calls: Vec<String>, // commands run via exec, joined with spaces
+    pub exec_failures: HashMap<String, u64>, // exit code for commands whose last argument matches
+    pub hung: bool, // ping, inspect and exec never answer, like a wedged daemon
+    next_id: u64,
+}
+
+/// In-memory Docker daemon for tests
+#[derive(Default)]
+pub struct MockBackend {
+    state: Mutex<MockState>,
+}
+
+impl MockBackend {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    pub fn state(&self) -> MutexGuard<'_, MockState> {
+        self.state.lock().unwrap()
+    }
+
+    pub fn with_container<T>(&self, id: &str, f: impl FnOnce(&mut MockContainer) -> T) -> Result<T, anyhow::Error> {
+        let mut state = self.state();
+        let container = state
+            .containers
+            .get_mut(id)
+            .ok_or_else(|| anyhow::anyhow!("No such container: {}", id))?;
+        Ok(f(container))
+    }
+
+    async fn hang_if_hung(&self) {
+        let hung = self.state().hung;
+        if hung {
+            std::future::pending::<()>().await;
+        }
+    }
+}
+
+#[async_trait]
+impl DockerBackend for MockBackend {
+    async fn ping(&self) -> Result<(), anyhow::Error> {
+        self.hang_if_hung().await;
+        Ok(())
+    }
+
+    async fn engine_version(&self) -> Result<String, anyhow::Error> {
+        Ok("Mock Engine 1.0".to_string())
+    }
+
+    async fn image_exists(&self, image: &str) -> Result<bool, anyhow::Error> {
+        Ok(self.state().pulled.iter().any(|pulled| pulled == image))
+    }
+
+    async fn image_command(&self, _image: &str) -> Result<(Vec<String>, Vec<String>), anyhow::Error> {
+        Ok((vec!["docker-entrypoint.sh".to_string()], vec!["postgres".to_string()]))
+    }
+
+    async fn pull_image(&self, image: &str) -> Result<(), anyhow::Error> {
+        self.state().pulled.push(image.to_string());
+        Ok(())
+    }
+
+    async fn create_container(&self, spec: &ContainerSpec) -> Result<String, anyhow::Error> {
+        let mut state = self.state();
+        if let Some(name) = &spec.name {
+            if state.containers.values().any(|c| c.spec.name.as_ref() == Some(name)) {
+                return Err(anyhow::anyhow!("The container name \"/{}\" is already in use by container", name));
+            }
+        }
+        if let Some(network) = spec.network.as_ref().filter(|network| !state.networks.contains(*network)) {
+            return Err(anyhow::anyhow!("network {} not found", network));
+        }
+        // Named volumes are created on first use, like Docker does
+        for volume in &spec.volumes {
+            if let Some((source, _)) = volume.split_once(':').filter(|(source, _)| !source.starts_with('/')) {
+                state.volumes.insert(source.to_string());
+            }
+        }
+
+        state.next_id += 1;
+        let id = format!("{:064x}", state.next_id);
+        state.containers.insert(id.clone(), MockContainer {
+            spec: spec.clone(),
+            running: false,
+            exit_code: None,
+            start_calls: 0,
+            files: HashMap::new(),
+            logs: Vec::new(),
+            health: None,
+            stats: ContainerStats::default(),
+        });
+        Ok(id)
+    }
+
+    async fn start_container(&self, id: &str) -> Result<(), anyhow::Error> {
+        if self.state().fail_start {
+            return Err(anyhow::anyhow!("Docker Error: failed to start container (500)"));
+        }
+        self.with_container(id, |container| {
+            container.running = true;
+            container.start_calls += 1;
+        })
+    }
+
+    async fn stop_container(&self, id: &str, _timeout_secs: u64) -> Result<(), anyhow::Error> {
+        self.with_container(id, |container| container.running = false)
+    }
+
+    async fn wait_container(&self, id: &str) -> Result<u64, anyhow::Error> {
+        self.with_container(id, |container| container.running = false)?;
+        Ok(0)
+    }
+
+    async fn rename_container(&self, id: &str, new_name: &str) -> Result<(), anyhow::Error> {
+        self.with_container(id, |container| container.spec.name = Some(new_name.to_string()))
+    }
+
+    async fn remove_container(&self, id: &str, _remove_volumes: bool) -> Result<(), anyhow::Error> {
+        let mut state = self.state();
+        if state.fail_remove.contains(id) {
+            return Err(anyhow::anyhow!("container {} is in use", id));
+        }
+        state
+            .containers
+            .remove(id)
+            .map(|_| ())
+            .ok_or_else(|| anyhow::anyhow!("No such container: {}", id))
+    }
+
+    async fn list_container_ids(&self, include_stopped: bool) -> Result<HashSet<String>, anyhow::Error> {
+        Ok(self
+            .state()
+            .containers
+            .iter()
+            .filter(|(_, container)| include_stopped || container.running)
+            .map(|(id, _)| id.clone())
+            .collect())
+    }
+
+    async fn list_running_containers(&self) -> Result<Vec<ContainerSummary>, anyhow::Error> {
+        Ok(self
+            .state()
+            .containers
+            .iter()
+            .filter(|(_, container)| container.running)
+            .map(|(id, container)| ContainerSummary {
+                id: id.clone(),
+                name: container.spec.name.clone().unwrap_or_default(),
+                image: container.spec.image.clone(),
+                ports: container.spec.ports.iter().map(|&(from, to)| (from as u16, to as u16)).collect(),
+                health: container.health.clone(),
+                labels: container.spec.labels.clone(),
+            })
+            .collect())
+    }
+
+    async fn container_mounts(&self, id: &str) -> Result<Vec<(String, String)>, anyhow::Error> {
+        // Named volumes show up under the daemon's volume directory, like Docker reports them
+        self.with_container(id, |container| {
+            container
+                .spec
+                .volumes
+                .iter()
+                .filter_map(|volume| volume.split_once(':'))
+                .map(|(source, target)| {
+                    let source = if source.starts_with('/') {
+                        source.to_string()
+                    } else {
+                        format!("/var/lib/docker/volumes/{}/_data", source)
+                    };
+                    (source, target.split(':').next().unwrap_or(target).to_string())
+                })
+                .collect()
+        })
+    }
+
+    async fn container_env(&self, id: &str) -> Result<Vec<String>, anyhow::Error> {
+        self.with_container(id, |container| container.spec.env.clone())
+    }
+
+    async fn container_labels(&self, id: &str) -> Result<HashMap<String, String>, anyhow::Error> {
+        self.with_container(id, |container| container.spec.labels.clone())
+    }
+
+    async fn inspect_state(&self, id: &str) -> Result<ContainerState, anyhow::Error> {
+        self.hang_if_hung().await;
+        self.with_container(id, |container| ContainerState {
+            running: container.running,
+            status: match (container.running, container.exit_code) {
+                (true, _) => "running",
+                (false, Some(_)) => "exited",
+                (false, None) => "created",
+            }
+            .to_string(),
+            exit_code: container.exit_code.unwrap_or(0),
+            oom_killed: false,
+            health: container.health.clone(),
+            health_output: container.health.as_ref().map(|health| format!("probe {}", health)),
+        })
+    }
+
+    async fn resolve_container(&self, name_or_id: &str) -> Result<String, anyhow::Error> {
+        self.state()
+            .containers
+            .iter()
+            .find(|(id, container)| *id == name_or_id || container.spec.name.as_deref() == Some(name_or_id))
+            .map(|(id, _)| id.clone())
+            .ok_or_else(|| anyhow::anyhow!("No such container: {}", name_or_id))
+    }
+
+    async fn container_stats(&self, id: &str) -> Result<ContainerStats, anyhow::Error> {
+        self.with_container(id, |container| container.stats.clone())
+    }
+
+    async fn list_volumes(&self) -> Result<Vec<String>, anyhow::Error> {
+        Ok(self.state().volumes.iter().cloned().collect())
+    }
+
+    async fn remove_volume(&self, name: &str) -> Result<(), anyhow::Error> {
+        if self.state().volumes.remove(name) {
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!("No such volume: {}", name))
+        }
+    }
+
+    async fn list_networks(&self) -> Result<Vec<String>, anyhow::Error> {
+        Ok(self.state().networks.iter().cloned().collect())
+    }
+
+    async fn create_network(&self, name: &str, _labels: &HashMap<String, String>) -> Result<(), anyhow::Error> {
+        if self.state().networks.insert(name.to_string()) {
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!("network with name {} already exists", name))
+        }
+    }
+
+    async fn remove_network(&self, name: &str) -> Result<(), anyhow::Error> {
+        let mut state = self.state();
+        if state.containers.values().any(|container| container.spec.network.as_deref() == Some(name)) {
+            return Err(anyhow::anyhow!("error while removing network: network {} has active endpoints", name));
+        }
+        if state.networks.remove(name) {
+            Ok(())
+        } else {
+            Err(anyhow::anyhow!("No such network: {}", name))
+        }
+    }
+
+    async fn logs(&self, id: &str, _tail: Option<usize>) -> Result<Vec<LogChunk>, anyhow::Error> {
+        self.with_container(id, |container| container.logs.clone())
+    }
+
+    async fn follow_logs(
+        &self,
+        id: &str,
+        on_chunk: &mut (dyn FnMut(LogChunk) -> bool + Send),
+    ) -> Result<(), anyhow::Error> {
+        for chunk in self.logs(id, None).await? {
+            if !on_chunk(chunk) {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    async fn exec(
+        &self,
+        id: &str,
+        cmd: &[&str],
+        env: &[&str],
+        on_chunk: &mut (dyn FnMut(LogChunk) + Send),
+    ) -> Result<u64, anyhow::Error> {
+        self.hang_if_hung().await;
+        self.with_container(id, |_| ())?;
+        let mut state = self.state();
+        state.exec_calls.push(cmd.join(" "));
+        match cmd.last().and_then(|last| state.exec_failures.get(*last)) {
+            Some(&code) => {
+                on_chunk(LogChunk::Stderr(format!("exit {}\n", code).into_bytes()));
+                Ok(code)
+            }
+            None => {
+                // A file written from inside the container, to the path given last
+                let prefix = format!("{}=", super::FILE_CONTENT_VAR);
+                if let (Some(content), Some(path)) = (env.iter().find_map(|var| var.strip_prefix(prefix.as_str())), cmd.last()) {
+                    if let Some(container) = state.containers.get_mut(id) {
+                        container.files.insert(PathBuf::from(path), content.as_bytes().to_vec());
+                    }
+                }
+                Ok(0)
+            }
+        }
+    }
+
+    async fn copy_file_into(&self, id: &str, path: &Path, bytes: &[u8]) -> Result<(), anyhow::Error> {
+        self.with_container(id, |container| {
+            container.files.insert(path.to_path_buf(), bytes.to_vec());
+        })
+    }
+}
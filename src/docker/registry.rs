@@ -0,0 +1,97 @@
+use shiplift::RegistryAuth;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+// The key `docker login` uses for Docker Hub in config.json
+const DOCKER_HUB: &str = "https://index.docker.io/v1/";
+
+/// The registry an image is pulled from: the first path component when it
+/// looks like a host (has a dot or port, or is localhost), Docker Hub otherwise
+pub fn registry_host(image: &str) -> &str {
+    match image.split_once('/') {
+        Some((first, _)) if first.contains('.') || first.contains(':') || first == "localhost" => first,
+        _ => DOCKER_HUB,
+    }
+}
+
+/// Credentials for the image's registry from the Docker CLI config
+/// (`$DOCKER_CONFIG/config.json`, else `~/.docker/config.json`), as stored by
+/// `docker login`. None pulls anonymously.
+pub fn registry_auth(image: &str) -> Option<RegistryAuth> {
+    let path = match std::env::var_os("DOCKER_CONFIG") {
+        Some(dir) => PathBuf::from(dir).join("config.json"),
+        None => directories::BaseDirs::new()?.home_dir().join(".docker").join("config.json"),
+    };
+    let content = std::fs::read_to_string(&path).ok()?;
+    let config: serde_json::Value = serde_json::from_str(&content)
+        .map_err(|e| tracing::warn!("Ignoring {}: {}", path.display(), e))
+        .ok()?;
+    auth_from_config(&config, registry_host(image), run_credential_helper)
+}
+
+/// Look up `registry` in a parsed config.json. A credential helper
+/// (`credHelpers` for the registry, else `credsStore`) takes precedence over
+/// the inline `auths` entry, like in the Docker CLI; `helper` runs it.
+pub fn auth_from_config(
+    config: &serde_json::Value,
+    registry: &str,
+    helper: impl Fn(&str, &str) -> Option<(String, String)>,
+) -> Option<RegistryAuth> {
+    let helper_name = config["credHelpers"][registry].as_str().or_else(|| config["credsStore"].as_str());
+    if let Some((username, secret)) = helper_name.and_then(|name| helper(name, registry)) {
+        return Some(credentials(registry, username, secret));
+    }
+
+    let auths = config["auths"].as_object()?;
+    let entry = auths.iter().find_map(|(key, entry)| (auth_key_host(key) == auth_key_host(registry)).then_some(entry))?;
+    if let Some(token) = entry["identitytoken"].as_str().filter(|token| !token.is_empty()) {
+        return Some(RegistryAuth::token(token));
+    }
+    let decoded = base64::decode(entry["auth"].as_str()?).ok()?;
+    let (username, password) = String::from_utf8(decoded).ok()?.split_once(':').map(|(u, p)| (u.to_string(), p.to_string()))?;
+    Some(credentials(registry, username, password))
+}
+
+// `auths` keys may be written with or without a scheme and path, and Docker
+// Hub has several names
+fn auth_key_host(key: &str) -> &str {
+    let host = key.trim_start_matches("https://").trim_start_matches("http://");
+    match host.split('/').next().unwrap_or(host) {
+        "docker.io" | "registry-1.docker.io" => "index.docker.io",
+        host => host,
+    }
+}
+
+// Helpers return "<token>" as the username for identity tokens
+fn credentials(registry: &str, username: String, secret: String) -> RegistryAuth {
+    if username == "<token>" {
+        RegistryAuth::token(secret)
+    } else {
+        RegistryAuth::builder()
+            .username(username)
+            .password(secret)
+            .server_address(registry)
+            .build()
+    }
+}
+
+/// Run `docker-credential-<name> get` for a registry and return (username, secret)
+fn run_credential_helper(name: &str, registry: &str) -> Option<(String, String)> {
+    let mut child = Command::new(format!("docker-credential-{}", name))
+        .arg("get")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| tracing::warn!("Credential helper docker-credential-{} not available: {}", name, e))
+        .ok()?;
+    child.stdin.take()?.write_all(registry.as_bytes()).ok()?;
+    let output = child.wait_with_output().ok()?;
+    // A registry the helper has nothing for is not an error
+    if !output.status.success() {
+        return None;
+    }
+    let reply: serde_json::Value = serde_json::from_slice(&output.stdout).ok()?;
+    Some((reply["Username"].as_str()?.to_string(), reply["Secret"].as_str()?.to_string()))
+}
@@ -0,0 +1,115 @@
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use crate::credentials::{generate_password, get_groups_path, DbCredentials};
+use crate::database::{CreateDatabaseStep, DbTemplate};
+
+/// A database in a group definition. Everything but the name and type is
+/// optional: passwords are generated, the database is named after the member
+/// and the port is the first free one from the type's default.
+#[derive(Deserialize, Clone, Debug)]
+pub struct GroupMember {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub db_type: String,
+    #[serde(default)]
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    #[serde(default)]
+    pub database: Option<String>,
+    #[serde(default)]
+    pub root_password: Option<String>,
+    #[serde(default)]
+    pub image: Option<String>, // defaults to the template image
+}
+
+/// Databases created, started, stopped and deleted together, on one network
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct GroupDefinition {
+    #[serde(default)]
+    pub databases: Vec<GroupMember>,
+}
+
+#[derive(Deserialize)]
+struct GroupsFile {
+    #[serde(default)]
+    groups: BTreeMap<String, GroupDefinition>,
+}
+
+/// The group definitions in groups.toml, by name; none if the file is missing
+pub fn load_groups() -> Result<BTreeMap<String, GroupDefinition>, anyhow::Error> {
+    let path = get_groups_path();
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+        Err(e) => return Err(anyhow::anyhow!("Cannot read {}: {}", path.display(), e)),
+    };
+    parse_groups(&content).map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))
+}
+
+pub fn parse_groups(content: &str) -> Result<BTreeMap<String, GroupDefinition>, anyhow::Error> {
+    let groups = toml::from_str::<GroupsFile>(content)?.groups;
+    for (name, group) in &groups {
+        // The name is part of the network name, which Docker restricts
+        let valid = name.chars().next().is_some_and(|c| c.is_ascii_alphanumeric())
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'));
+        if !valid {
+            return Err(anyhow::anyhow!("Group name '{}' may only contain letters, digits, '_', '.' and '-'", name));
+        }
+        if group.databases.is_empty() {
+            return Err(anyhow::anyhow!("Group '{}' lists no databases", name));
+        }
+        let mut seen = Vec::new();
+        for member in &group.databases {
+            if seen.contains(&&member.name) {
+                return Err(anyhow::anyhow!("Group '{}' lists '{}' twice", name, member.name));
+            }
+            seen.push(&member.name);
+        }
+    }
+    Ok(groups)
+}
+
+/// The Docker network a group's containers share; members reach each other
+/// by database name on it
+pub fn network_name(group: &str) -> String {
+    format!("{}_net", group)
+}
+
+impl GroupMember {
+    /// Credentials for the member on `port`, filling in what the definition
+    /// leaves out. Only what the template uses is set, like in the create form.
+    pub fn credentials(&self, template: &DbTemplate, port: u16) -> DbCredentials {
+        let steps = template.required_steps();
+        let default_username = if self.db_type.eq_ignore_ascii_case("neo4j") { "neo4j" } else { "app" };
+        DbCredentials {
+            username: if steps.contains(&CreateDatabaseStep::Username) {
+                self.username.clone().unwrap_or_else(|| default_username.to_string())
+            } else {
+                String::new()
+            },
+            password: if steps.contains(&CreateDatabaseStep::Password) {
+                self.password.clone().unwrap_or_else(generate_password)
+            } else {
+                String::new()
+            },
+            database: if steps.contains(&CreateDatabaseStep::Database) {
+                self.database.clone().unwrap_or_else(|| self.name.replace('-', "_"))
+            } else {
+                String::new()
+            },
+            port,
+            root_password: if steps.contains(&CreateDatabaseStep::RootPassword) {
+                Some(self.root_password.clone().unwrap_or_else(generate_password))
+            } else {
+                None
+            },
+            connect_database: None,
+            connection_template: None,
+            password_source: None,
+        }
+    }
+}
@@ -0,0 +1,38 @@
+use std::fs::OpenOptions;
+use std::io::IsTerminal;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tracing_subscriber::EnvFilter;
+
+/// Where logs go: stderr for the command-line modes, a file while the TUI
+/// owns the terminal (anything written to stderr would corrupt the display)
+pub enum LogTarget {
+    Stderr,
+    File(PathBuf),
+}
+
+/// Install the global subscriber. `RUST_LOG` takes precedence; otherwise
+/// `--verbose` logs db-tool's own debug events and the default is warnings only.
+pub fn init(verbose: bool, target: LogTarget) -> Result<(), anyhow::Error> {
+    let filter = EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| EnvFilter::new(if verbose { "db_tool=debug" } else { "warn" }));
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_target(false);
+
+    match target {
+        LogTarget::Stderr => builder
+            .with_ansi(std::io::stderr().is_terminal())
+            .with_writer(std::io::stderr)
+            .init(),
+        LogTarget::File(path) => {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)
+                .map_err(|e| anyhow::anyhow!("Cannot open log file '{}': {}", path.display(), e))?;
+            builder.with_ansi(false).with_writer(Mutex::new(file)).init();
+        }
+    }
+    Ok(())
+}
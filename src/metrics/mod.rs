@@ -0,0 +1,104 @@
+use std::fmt::Write;
+use crate::credentials::AppConfig;
+use crate::docker::{ContainerStats, DockerManager};
+
+/// What one database contributes to the metrics
+pub struct DatabaseSample {
+    pub name: String,
+    pub db_type: String,
+    pub up: bool,
+    pub stats: Option<ContainerStats>, // running containers only
+}
+
+/// Sample every stored database: whether its container runs and, if so, its
+/// memory and CPU use. A container that is gone counts as down; external
+/// databases have none and are left out.
+pub async fn collect(config: &AppConfig, passphrase: &str, docker_manager: &DockerManager) -> Result<Vec<DatabaseSample>, anyhow::Error> {
+    let mut databases = config.get_all_databases(passphrase)?;
+    databases.sort_by(|a, b| a.name.cmp(&b.name));
+    let running = docker_manager.running_container_ids().await?;
+
+    let mut samples = Vec::new();
+    for db in databases.into_iter().filter(|db| !db.external) {
+        let up = running.contains(&db.container_id);
+        let stats = if up {
+            // It may have stopped since the list was taken
+            docker_manager
+                .container_stats(&db.container_id)
+                .await
+                .map_err(|e| tracing::warn!("No stats for '{}': {}", db.name, e))
+                .ok()
+        } else {
+            None
+        };
+        samples.push(DatabaseSample {
+            name: db.name,
+            db_type: db.db_type.template_name().to_string(),
+            up,
+            stats,
+        });
+    }
+    Ok(samples)
+}
+
+/// The samples in the Prometheus text exposition format
+pub fn render(samples: &[DatabaseSample]) -> String {
+    type Value = fn(&DatabaseSample) -> Option<String>;
+    let metrics: [(&str, &str, &str, Value); 4] = [
+        ("db_manager_container_up", "gauge", "Whether the database's container is running.", |sample| {
+            Some(u8::from(sample.up).to_string())
+        }),
+        ("db_manager_container_memory_usage_bytes", "gauge", "Memory used by the container, without the page cache.", |sample| {
+            sample.stats.as_ref().map(|stats| stats.memory_usage.to_string())
+        }),
+        ("db_manager_container_memory_limit_bytes", "gauge", "Memory the container may use.", |sample| {
+            sample.stats.as_ref().map(|stats| stats.memory_limit.to_string())
+        }),
+        ("db_manager_container_cpu_seconds_total", "counter", "CPU time used by the container since it started.", |sample| {
+            sample.stats.as_ref().map(|stats| stats.cpu_seconds.to_string())
+        }),
+    ];
+
+    let mut output = String::new();
+    for (metric, kind, help, value) in metrics {
+        let _ = writeln!(output, "# HELP {} {}", metric, help);
+        let _ = writeln!(output, "# TYPE {} {}", metric, kind);
+        for sample in samples {
+            if let Some(value) = value(sample) {
+                let _ = writeln!(output, "{}{{name=\"{}\",type=\"{}\"}} {}", metric, escape_label(&sample.name), escape_label(&sample.db_type), value);
+            }
+        }
+    }
+    output
+}
+
+// Label values escape backslashes, double quotes and newlines
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn samples_are_rendered_in_the_text_format() {
+        let samples = [
+            DatabaseSample {
+                name: "orders".to_string(),
+                db_type: "postgres".to_string(),
+                up: true,
+                stats: Some(ContainerStats { memory_usage: 52_428_800, memory_limit: 1_073_741_824, cpu_seconds: 12.5 }),
+            },
+            DatabaseSample { name: "odd \"name\"".to_string(), db_type: "redis".to_string(), up: false, stats: None },
+        ];
+        let output = render(&samples);
+        assert!(output.contains("# TYPE db_manager_container_up gauge\n"));
+        assert!(output.contains("db_manager_container_up{name=\"orders\",type=\"postgres\"} 1\n"));
+        assert!(output.contains("db_manager_container_up{name=\"odd \\\"name\\\"\",type=\"redis\"} 0\n"));
+        assert!(output.contains("db_manager_container_memory_usage_bytes{name=\"orders\",type=\"postgres\"} 52428800\n"));
+        assert!(output.contains("db_manager_container_cpu_seconds_total{name=\"orders\",type=\"postgres\"} 12.5\n"));
+        // A stopped container has no resource figures rather than zeros
+        assert_eq!(output.matches("type=\"redis\"").count(), 1);
+    }
+}
@@ -0,0 +1,192 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use crate::credentials::DbCredentials;
+use crate::database::DbType;
+
+/// A password file a command-line client reads, so it connects without
+/// prompting: `~/.pgpass` for psql, `~/.my.cnf` for mysql
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PasswordFile {
+    PgPass,
+    MyCnf,
+}
+
+impl PasswordFile {
+    pub fn for_type(db_type: &DbType) -> Option<Self> {
+        match db_type {
+            DbType::Postgres => Some(PasswordFile::PgPass),
+            DbType::MySQL => Some(PasswordFile::MyCnf),
+            _ => None,
+        }
+    }
+
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            PasswordFile::PgPass => ".pgpass",
+            PasswordFile::MyCnf => ".my.cnf",
+        }
+    }
+
+    /// `PGPASSFILE` if set (libpq reads it instead of ~/.pgpass), else the
+    /// file in the home directory
+    pub fn path(&self) -> Option<PathBuf> {
+        if *self == PasswordFile::PgPass {
+            if let Some(path) = std::env::var_os("PGPASSFILE").filter(|path| !path.is_empty()) {
+                return Some(PathBuf::from(path));
+            }
+        }
+        Some(directories::BaseDirs::new()?.home_dir().join(self.file_name()))
+    }
+
+    /// Add the database's entry to the file, replacing an earlier one for the
+    /// same server and user. The file is written with mode 0600: libpq ignores
+    /// a .pgpass others can read, and both hold passwords in plain text.
+    pub fn write_entry(&self, name: &str, credentials: &DbCredentials) -> Result<PathBuf, anyhow::Error> {
+        let path = self.path().ok_or_else(|| anyhow::anyhow!("No home directory to write {} to", self.file_name()))?;
+        // Write through a symlink, e.g. into a dotfiles repository, rather than replacing it
+        let path = fs::canonicalize(&path).unwrap_or(path);
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(anyhow::anyhow!("Failed to read {}: {}", path.display(), e)),
+        };
+        let updated = match self {
+            PasswordFile::PgPass => upsert_pgpass(&content, credentials),
+            PasswordFile::MyCnf => upsert_my_cnf(&content, name, credentials),
+        };
+        write_private(&path, &updated).map_err(|e| anyhow::anyhow!("Failed to write {}: {}", path.display(), e))?;
+        tracing::info!("Wrote the {} entry for '{}' to {}", self.file_name(), name, path.display());
+        Ok(path)
+    }
+
+    /// How to connect with the entry in place
+    pub fn usage(&self, name: &str, credentials: &DbCredentials) -> String {
+        match self {
+            PasswordFile::PgPass => format!(
+                "psql -h localhost -p {} -U {} {}",
+                credentials.port, credentials.username, credentials.connect_database()
+            ),
+            PasswordFile::MyCnf => format!("mysql --defaults-group-suffix={}", my_cnf_suffix(name)),
+        }
+    }
+}
+
+/// `.pgpass` takes `hostname:port:database:username:password` lines. The
+/// database is `*`, since the password belongs to the user rather than to
+/// one database.
+pub fn upsert_pgpass(content: &str, credentials: &DbCredentials) -> String {
+    let key = format!("localhost:{}:*:{}:", credentials.port, pgpass_escape(&credentials.username));
+    let mut lines: Vec<String> = content
+        .lines()
+        .filter(|line| !line.starts_with(&key))
+        .map(str::to_string)
+        .collect();
+    lines.push(format!("{}{}", key, pgpass_escape(&credentials.password)));
+    lines.join("\n") + "\n"
+}
+
+// `:` and `\` are escaped with a backslash
+fn pgpass_escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace(':', "\\:")
+}
+
+/// `.my.cnf` gets a `[client_<name>]` group, which clients only read with
+/// `--defaults-group-suffix=_<name>`, so a `[client]` group already in the
+/// file keeps applying to everything else
+pub fn upsert_my_cnf(content: &str, name: &str, credentials: &DbCredentials) -> String {
+    let header = format!("[client{}]", my_cnf_suffix(name));
+    let mut lines = Vec::new();
+    let mut in_group = false;
+    for line in content.lines() {
+        if line.trim_start().starts_with('[') {
+            in_group = line.trim() == header;
+        }
+        if !in_group {
+            lines.push(line.to_string());
+        }
+    }
+    while lines.last().is_some_and(|line| line.trim().is_empty()) {
+        lines.pop();
+    }
+    if !lines.is_empty() {
+        lines.push(String::new());
+    }
+    lines.extend([
+        header,
+        // 127.0.0.1 rather than localhost, which the client takes to mean the Unix socket
+        "host=127.0.0.1".to_string(),
+        format!("port={}", credentials.port),
+        format!("user={}", my_cnf_quote(&credentials.username)),
+        format!("password={}", my_cnf_quote(&credentials.password)),
+    ]);
+    lines.join("\n") + "\n"
+}
+
+fn my_cnf_suffix(name: &str) -> String {
+    format!("_{}", name)
+}
+
+// Double-quoted, so `#` and surrounding spaces are kept
+fn my_cnf_quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+// Written next to the file and renamed over it, created with 0600 so the
+// secret is never readable by others, not even briefly
+fn write_private(path: &std::path::Path, content: &str) -> std::io::Result<()> {
+    let temp_path = path.with_extension("db-manager.tmp");
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        // The mode only applies when the file is created
+        if temp_path.exists() {
+            fs::set_permissions(&temp_path, fs::Permissions::from_mode(0o600))?;
+        }
+    }
+    let written = options
+        .open(&temp_path)
+        .and_then(|mut file| file.write_all(content.as_bytes()))
+        .and_then(|()| fs::rename(&temp_path, path));
+    if written.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    written
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn credentials() -> DbCredentials {
+        DbCredentials {
+            username: "app".to_string(),
+            password: "p@ss:w\\rd".to_string(),
+            database: "appdb".to_string(),
+            port: 15432,
+            root_password: None,
+            connect_database: None,
+            connection_template: None,
+            password_source: None,
+        }
+    }
+
+    #[test]
+    fn entries_replace_earlier_ones_for_the_same_server() {
+        let existing = "# work\ndb.example.com:5432:*:me:secret\nlocalhost:15432:*:app:old\n";
+        assert_eq!(
+            upsert_pgpass(existing, &credentials()),
+            "# work\ndb.example.com:5432:*:me:secret\nlocalhost:15432:*:app:p@ss\\:w\\\\rd\n"
+        );
+
+        let existing = "[client]\nuser=me\n\n[client_orders]\nport=1\npassword=old\n\n[mysqldump]\nquick\n";
+        assert_eq!(
+            upsert_my_cnf(existing, "orders", &credentials()),
+            "[client]\nuser=me\n\n[mysqldump]\nquick\n\n[client_orders]\nhost=127.0.0.1\nport=15432\nuser=\"app\"\npassword=\"p@ss:w\\\\rd\"\n"
+        );
+        assert!(upsert_my_cnf("", "orders", &credentials()).starts_with("[client_orders]\n"));
+    }
+}
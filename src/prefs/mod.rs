@@ -0,0 +1,110 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::time::Duration;
+use crate::credentials::get_prefs_path;
+use crate::database::get_db_templates;
+
+// How often container status is re-checked unless configured
+const DEFAULT_STATUS_REFRESH_SECS: u64 = 5;
+
+// How long a Docker call may take unless configured
+const DEFAULT_DOCKER_TIMEOUT_SECS: u64 = 30;
+
+// Overrides `default_type`, e.g. set team-wide in a shell profile
+const DEFAULT_TYPE_ENV_VAR: &str = "DB_MANAGER_DEFAULT_TYPE";
+const FALLBACK_TYPE: &str = "postgres";
+
+/// Display preferences. Nothing secret, so they're stored unencrypted next to
+/// the config and are available before authentication.
+#[derive(Serialize, Deserialize, Default)]
+pub struct UiPrefs {
+    #[serde(default)]
+    pub no_color: bool, // plain list without per-type colors
+    #[serde(default)]
+    pub default_username: Option<String>, // pre-filled in the create form
+    #[serde(default)]
+    pub default_ports: HashMap<String, u16>, // per-type port used instead of the template's
+    #[serde(default)]
+    pub default_type: Option<String>, // type the create form starts on
+    #[serde(default)]
+    pub status_refresh_secs: Option<u64>, // container status polling in the list and details; 0 turns it off
+    #[serde(default)]
+    pub favorites: BTreeSet<String>, // database names pinned to the top of the list
+    #[serde(default)]
+    pub docker_timeout_secs: Option<u64>, // limit for each Docker call that should answer promptly
+}
+
+impl UiPrefs {
+    /// Load the prefs, falling back to defaults if the file is missing or unreadable
+    pub fn load() -> Self {
+        fs::read_to_string(get_prefs_path())
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), anyhow::Error> {
+        let content = serde_json::to_string_pretty(self)?;
+        fs::write(get_prefs_path(), content)?;
+        Ok(())
+    }
+
+    /// How often to re-check container status, or None if polling is off
+    pub fn status_refresh(&self) -> Option<Duration> {
+        match self.status_refresh_secs.unwrap_or(DEFAULT_STATUS_REFRESH_SECS) {
+            0 => None,
+            secs => Some(Duration::from_secs(secs)),
+        }
+    }
+
+    /// How long a Docker call may take before it fails as timed out; at least a second
+    pub fn docker_timeout(&self) -> Duration {
+        Duration::from_secs(self.docker_timeout_secs.unwrap_or(DEFAULT_DOCKER_TIMEOUT_SECS).max(1))
+    }
+
+    /// The type the create form starts on: `DB_MANAGER_DEFAULT_TYPE`, else
+    /// `default_type`, as long as it names an available template; postgres otherwise
+    pub fn default_type(&self) -> String {
+        let configured = std::env::var(DEFAULT_TYPE_ENV_VAR)
+            .ok()
+            .filter(|value| !value.is_empty())
+            .or_else(|| self.default_type.clone());
+        match configured {
+            Some(db_type) if get_db_templates().contains_key(&db_type) => db_type,
+            Some(db_type) => {
+                tracing::warn!("Default database type '{}' has no template; using {}", db_type, FALLBACK_TYPE);
+                FALLBACK_TYPE.to_string()
+            }
+            None => FALLBACK_TYPE.to_string(),
+        }
+    }
+
+    pub fn is_favorite(&self, name: &str) -> bool {
+        self.favorites.contains(name)
+    }
+
+    /// Pin or unpin a database; returns whether it is pinned now
+    pub fn toggle_favorite(&mut self, name: &str) -> bool {
+        if self.favorites.remove(name) {
+            false
+        } else {
+            self.favorites.insert(name.to_string());
+            true
+        }
+    }
+
+    /// Keep a pin when its database is renamed; returns whether it had one
+    pub fn rename_favorite(&mut self, name: &str, new_name: &str) -> bool {
+        let pinned = self.favorites.remove(name);
+        if pinned {
+            self.favorites.insert(new_name.to_string());
+        }
+        pinned
+    }
+
+    /// Colors are off if disabled here or via the NO_COLOR convention
+    pub fn colors_enabled(&self) -> bool {
+        !self.no_color && std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+    }
+}
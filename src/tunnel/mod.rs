@@ -0,0 +1,170 @@
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::net::{TcpListener, TcpStream};
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+// How long ssh gets to log in and start listening
+const OPEN_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// An SSH host through which a database is reached. The local end of the
+/// forward is the port in the database's connection string, so the string and
+/// client command work unchanged once the tunnel is open.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SshTunnel {
+    pub destination: String, // [user@]host, as ssh takes it; ~/.ssh/config aliases work too
+    #[serde(default)]
+    pub ssh_port: Option<u16>, // None leaves it to ssh (22 or ~/.ssh/config)
+    #[serde(default = "default_remote_host")]
+    pub remote_host: String, // where the database listens, as seen from the SSH host
+    #[serde(default)]
+    pub remote_port: Option<u16>, // None is the database's own port
+}
+
+fn default_remote_host() -> String {
+    "localhost".to_string()
+}
+
+// `host[:port]`; a bare IPv6 address has no port
+fn split_port(value: &str) -> Result<(&str, Option<u16>), anyhow::Error> {
+    let (host, port) = match value.rsplit_once(':') {
+        Some((host, port)) if !host.contains(':') => (host, Some(port)),
+        _ => (value, None),
+    };
+    let port = port
+        .map(|port| port.parse::<u16>().ok().filter(|port| *port > 0).ok_or_else(|| anyhow::anyhow!("Invalid port in '{}'", value)))
+        .transpose()?;
+    if host.is_empty() || host.starts_with('-') || host.chars().any(char::is_whitespace) {
+        return Err(anyhow::anyhow!("Invalid host in '{}'", value));
+    }
+    Ok((host, port))
+}
+
+impl SshTunnel {
+    /// A tunnel from `--via [user@]host[:port]` and `--remote host[:port]`
+    pub fn parse(via: &str, remote: Option<&str>) -> Result<Self, anyhow::Error> {
+        let (destination, ssh_port) = split_port(via)?;
+        let (remote_host, remote_port) = match remote {
+            Some(remote) => split_port(remote)?,
+            None => ("localhost", None),
+        };
+        Ok(SshTunnel {
+            destination: destination.to_string(),
+            ssh_port,
+            remote_host: remote_host.to_string(),
+            remote_port,
+        })
+    }
+
+    /// Arguments for an ssh process that only forwards `local_port`
+    pub fn ssh_args(&self, local_port: u16) -> Vec<String> {
+        let mut args = vec![
+            "-N".to_string(),
+            // Fail instead of running without the forward, and never prompt:
+            // there is no terminal to answer on while the interface runs
+            "-o".to_string(),
+            "ExitOnForwardFailure=yes".to_string(),
+            "-o".to_string(),
+            "BatchMode=yes".to_string(),
+            "-L".to_string(),
+            format!("127.0.0.1:{}:{}:{}", local_port, self.remote_host, self.remote_port.unwrap_or(local_port)),
+        ];
+        if let Some(port) = self.ssh_port {
+            args.extend(["-p".to_string(), port.to_string()]);
+        }
+        args.push(self.destination.clone());
+        args
+    }
+
+    /// The ssh command line, to run the tunnel by hand
+    pub fn command_line(&self, local_port: u16) -> String {
+        format!("ssh {}", self.ssh_args(local_port).join(" "))
+    }
+
+    /// `localhost:15432 → db.internal:5432 via admin@bastion`
+    pub fn describe(&self, local_port: u16) -> String {
+        let via = match self.ssh_port {
+            Some(port) => format!("{}:{}", self.destination, port),
+            None => self.destination.clone(),
+        };
+        format!("localhost:{} → {}:{} via {}", local_port, self.remote_host, self.remote_port.unwrap_or(local_port), via)
+    }
+
+    /// Start ssh and wait until the forward accepts connections. The tunnel
+    /// stays open until the returned handle is dropped.
+    pub fn open(&self, local_port: u16) -> Result<OpenTunnel, anyhow::Error> {
+        // Otherwise a connection to whatever holds the port would look like success
+        TcpListener::bind(("127.0.0.1", local_port))
+            .map_err(|e| anyhow::anyhow!("Local port {} is not free for the tunnel: {}", local_port, e))?;
+
+        let child = Command::new("ssh")
+            .args(self.ssh_args(local_port))
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| anyhow::anyhow!("Failed to run ssh: {}", e))?;
+        let mut tunnel = OpenTunnel { child };
+        tracing::info!("Opening SSH tunnel {}", self.describe(local_port));
+
+        let started = Instant::now();
+        loop {
+            if let Some(status) = tunnel.child.try_wait()? {
+                let mut stderr = String::new();
+                if let Some(mut pipe) = tunnel.child.stderr.take() {
+                    let _ = pipe.read_to_string(&mut stderr);
+                }
+                return Err(anyhow::anyhow!("ssh exited ({}): {}", status, stderr.trim()));
+            }
+            if TcpStream::connect_timeout(&([127, 0, 0, 1], local_port).into(), Duration::from_millis(200)).is_ok() {
+                return Ok(tunnel);
+            }
+            if started.elapsed() > OPEN_TIMEOUT {
+                return Err(anyhow::anyhow!("ssh did not open the tunnel within {}s", OPEN_TIMEOUT.as_secs()));
+            }
+            std::thread::sleep(Duration::from_millis(100));
+        }
+    }
+}
+
+/// A running ssh forward; dropping it ends the process
+pub struct OpenTunnel {
+    child: Child,
+}
+
+impl OpenTunnel {
+    /// Whether ssh still runs, e.g. after the connection dropped
+    pub fn is_alive(&mut self) -> bool {
+        matches!(self.child.try_wait(), Ok(None))
+    }
+}
+
+impl Drop for OpenTunnel {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tunnels_are_parsed_into_ssh_forward_arguments() {
+        let tunnel = SshTunnel::parse("admin@bastion:2222", Some("db.internal:5432")).unwrap();
+        assert_eq!(
+            tunnel.command_line(15432),
+            "ssh -N -o ExitOnForwardFailure=yes -o BatchMode=yes -L 127.0.0.1:15432:db.internal:5432 -p 2222 admin@bastion"
+        );
+        assert_eq!(tunnel.describe(15432), "localhost:15432 → db.internal:5432 via admin@bastion:2222");
+
+        // The remote end defaults to the same port on the SSH host itself
+        let tunnel = SshTunnel::parse("bastion", None).unwrap();
+        assert_eq!(tunnel.ssh_args(6379)[6], "127.0.0.1:6379:localhost:6379");
+
+        assert!(SshTunnel::parse("-oProxyCommand=evil", None).is_err());
+        assert!(SshTunnel::parse("bastion:0", None).is_err());
+        assert!(SshTunnel::parse("bastion", Some("db:port")).is_err());
+    }
+}